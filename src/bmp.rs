@@ -6,24 +6,18 @@ use std::{
     path::Path,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum PixelFormat {
-    Rgb8,
-    Rgb8Srgb,
-    Rgba8,
-    Rgba8Srgb,
-    Bgra8,
-    Bgra8Srgb,
-}
-
-fn gamma_correct(u_in: u8, gamma: f32) -> u8 {
-    let f_in = (u_in as f32) / 255.0;
-    let f_out = f_in.powf(gamma);
-    (f_out * 255.0).floor() as u8
-}
+use crate::repack::{self, PixelFormat};
+use crate::{Channels, Colorspace, Header};
 
-fn srgb_to_rgb(srgb: u8) -> u8 {
-    gamma_correct(srgb, 1.0 / 2.2)
+/// Picks the `PixelFormat` variant matching `channels` and `colorspace`, so callers
+/// converting a decoded QOI image don't have to hardcode the sRGB-ness themselves.
+pub fn pixel_format_for(channels: Channels, colorspace: Colorspace) -> PixelFormat {
+    match (channels, colorspace) {
+        (Channels::Rgb, Colorspace::Rgb) => PixelFormat::Rgb8,
+        (Channels::Rgb, Colorspace::Srgb) => PixelFormat::Rgb8Srgb,
+        (Channels::Rgba, Colorspace::Rgb) => PixelFormat::Rgba8,
+        (Channels::Rgba, Colorspace::Srgb) => PixelFormat::Rgba8Srgb,
+    }
 }
 
 fn bmp_header(data: &mut Vec<u8>, width: u32, height: u32) {
@@ -72,66 +66,93 @@ pub fn encode_bmp_with<T>(
 }
 
 /// Encode an image data into BMP format.
+///
+/// `pixel_data` is repacked into linear BGRA8 (BMP's native layout) via the
+/// [`repack`] module before being written, so any supported `format` (including
+/// sRGB-encoded ones) is handled without bespoke conversion code here.
 pub fn encode_bmp(width: u32, height: u32, format: PixelFormat, pixel_data: &[u8]) -> Vec<u8> {
-    match format {
-        PixelFormat::Rgb8 => encode_bmp_with(
-            width,
-            height,
-            pixel_data.array_chunks::<3>().copied(),
-            |src| [src[2], src[1], src[0], 255],
-        ),
-        PixelFormat::Rgb8Srgb => encode_bmp_with(
-            width,
-            height,
-            pixel_data.array_chunks::<4>().copied(),
-            |src| {
-                [
-                    srgb_to_rgb(src[2]),
-                    srgb_to_rgb(src[1]),
-                    srgb_to_rgb(src[0]),
-                    255,
-                ]
-            },
-        ),
-        PixelFormat::Rgba8 => encode_bmp_with(
-            width,
-            height,
-            pixel_data.array_chunks::<4>().copied(),
-            |src| [src[2], src[1], src[0], src[3]],
-        ),
-        PixelFormat::Rgba8Srgb => encode_bmp_with(
-            width,
-            height,
-            pixel_data.array_chunks::<4>().copied(),
-            |src| {
-                [
-                    srgb_to_rgb(src[2]),
-                    srgb_to_rgb(src[1]),
-                    srgb_to_rgb(src[0]),
-                    src[3],
-                ]
-            },
-        ),
-        PixelFormat::Bgra8 => encode_bmp_with(
-            width,
-            height,
-            pixel_data.array_chunks::<4>().copied(),
-            |src| [src[0], src[1], src[2], src[3]],
-        ),
-        PixelFormat::Bgra8Srgb => encode_bmp_with(
-            width,
-            height,
-            pixel_data.array_chunks::<4>().copied(),
-            |src| {
-                [
-                    srgb_to_rgb(src[0]),
-                    srgb_to_rgb(src[1]),
-                    srgb_to_rgb(src[2]),
-                    src[3],
-                ]
-            },
-        ),
+    let bgra = repack::convert(format, PixelFormat::Bgra8, pixel_data);
+    encode_bmp_with(width, height, bgra.array_chunks::<4>().copied(), |bgra| {
+        bgra
+    })
+}
+
+#[derive(Debug, derive_more::Display, derive_more::From)]
+pub enum BmpDecodeError {
+    InvalidSignature,
+    Truncated,
+    InvalidDataOffset,
+    UnsupportedBitsPerPixel(u16),
+    UnsupportedCompression(u32),
+}
+
+impl std::error::Error for BmpDecodeError {}
+
+fn read_u16_le(data: &[u8], offset: usize) -> Result<u16, BmpDecodeError> {
+    let bytes = data.get(offset..offset + 2).ok_or(BmpDecodeError::Truncated)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32_le(data: &[u8], offset: usize) -> Result<u32, BmpDecodeError> {
+    let bytes = data.get(offset..offset + 4).ok_or(BmpDecodeError::Truncated)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Decodes a 24 or 32 bpp, uncompressed, bottom-up BMP (the layout [`encode_bmp`]
+/// writes) into RGBA8 pixels plus a populated `Header`.
+pub fn decode_bmp(data: &[u8]) -> Result<(Vec<u8>, Header), BmpDecodeError> {
+    if data.get(0..2) != Some(b"BM".as_slice()) {
+        return Err(BmpDecodeError::InvalidSignature);
+    }
+    let data_offset = read_u32_le(data, 10)? as usize;
+    let width = read_u32_le(data, 18)?;
+    let height = read_u32_le(data, 22)?;
+    let bits_per_pixel = read_u16_le(data, 28)?;
+    let compression = read_u32_le(data, 30)?;
+    if compression != 0 {
+        return Err(BmpDecodeError::UnsupportedCompression(compression));
+    }
+    let bytes_per_pixel = match bits_per_pixel {
+        24 => 3,
+        32 => 4,
+        other => return Err(BmpDecodeError::UnsupportedBitsPerPixel(other)),
+    };
+
+    let width = width as usize;
+    let height = height as usize;
+    let row_stride = (width * bytes_per_pixel).div_ceil(4) * 4;
+    let pixel_data = data
+        .get(data_offset..)
+        .ok_or(BmpDecodeError::InvalidDataOffset)?;
+    if pixel_data.len() < row_stride * height {
+        return Err(BmpDecodeError::InvalidDataOffset);
+    }
+
+    // Rows are bottom-up and possibly padded to a 4-byte boundary; unpack each into
+    // a tightly-packed BGRA8 buffer so `repack` can take it from there.
+    let mut bgra = vec![0u8; width * height * 4];
+    for y in 0..height {
+        let src_row = &pixel_data[y * row_stride..][..width * bytes_per_pixel];
+        let dst_row = &mut bgra[(height - 1 - y) * width * 4..][..width * 4];
+        for (src_pixel, dst_pixel) in src_row
+            .chunks_exact(bytes_per_pixel)
+            .zip(dst_row.chunks_exact_mut(4))
+        {
+            dst_pixel[0] = src_pixel[0];
+            dst_pixel[1] = src_pixel[1];
+            dst_pixel[2] = src_pixel[2];
+            dst_pixel[3] = if bytes_per_pixel == 4 { src_pixel[3] } else { 255 };
+        }
     }
+
+    let rgba = repack::convert(PixelFormat::Bgra8, PixelFormat::Rgba8, &bgra);
+    let header = Header {
+        width: width as u32,
+        height: height as u32,
+        channels: Channels::Rgba,
+        colorspace: Colorspace::Rgb,
+    };
+    Ok((rgba, header))
 }
 
 fn save_data(path: impl AsRef<Path>, data: &[u8]) -> io::Result<()> {
@@ -167,3 +188,72 @@ pub fn save_bmp_with<T>(
 ) -> io::Result<()> {
     save_data(path, &encode_bmp_with(width, height, pixels, f_encode))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode_roundtrip() {
+        let width = 3;
+        let height = 2;
+        let rgba: Vec<u8> = vec![
+            10, 20, 30, 255, 40, 50, 60, 255, 70, 80, 90, 255, 100, 110, 120, 255, 130, 140, 150,
+            255, 160, 170, 180, 255,
+        ];
+        let encoded = encode_bmp(width, height, PixelFormat::Rgba8, &rgba);
+        let (decoded, header) = decode_bmp(&encoded).unwrap();
+        assert_eq!(header.width, width);
+        assert_eq!(header.height, height);
+        assert_eq!(header.channels, Channels::Rgba);
+        assert_eq!(decoded, rgba);
+    }
+
+    /// A hand-built 24bpp BMP whose rows (3 pixels * 3 bytes = 9 bytes) are padded to
+    /// the next 4-byte boundary (12 bytes), to catch `row_stride` padding bugs.
+    #[test]
+    fn decode_24bpp_with_row_padding() {
+        let width = 3u32;
+        let height = 2u32;
+        // (R, G, B) per pixel, top row first, for readability; written to the file
+        // bottom-up and in BGR byte order below, as real BMPs store them.
+        let top_row = [[10u8, 20, 30], [40, 50, 60], [70, 80, 90]];
+        let bottom_row = [[100u8, 110, 120], [130, 140, 150], [160, 170, 180]];
+
+        let mut data = Vec::new();
+        data.extend_from_slice(b"BM");
+        data.extend_from_slice(&0u32.to_le_bytes()); /* File size (unused by decoder) */
+        data.extend_from_slice(&0u16.to_le_bytes()); /* Reserved */
+        data.extend_from_slice(&0u16.to_le_bytes()); /* Reserved */
+        data.extend_from_slice(&54u32.to_le_bytes()); /* Data offset */
+        data.extend_from_slice(&40u32.to_le_bytes()); /* Size of InfoHeader */
+        data.extend_from_slice(&width.to_le_bytes());
+        data.extend_from_slice(&height.to_le_bytes());
+        data.extend_from_slice(&1u16.to_le_bytes()); /* Planes */
+        data.extend_from_slice(&24u16.to_le_bytes()); /* Bits per pixel */
+        data.extend_from_slice(&0u32.to_le_bytes()); /* Compression */
+        data.extend_from_slice(&0u32.to_le_bytes()); /* Image size */
+        data.extend_from_slice(&2835u32.to_le_bytes());
+        data.extend_from_slice(&2835u32.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        assert_eq!(data.len(), 54);
+
+        for row in [bottom_row, top_row] {
+            for [r, g, b] in row {
+                data.extend_from_slice(&[b, g, r]);
+            }
+            data.extend_from_slice(&[0, 0, 0]); /* Row padding to a 4-byte boundary */
+        }
+
+        let (decoded, header) = decode_bmp(&data).unwrap();
+        assert_eq!(header.width, width);
+        assert_eq!(header.height, height);
+        let expected: Vec<u8> = top_row
+            .into_iter()
+            .chain(bottom_row)
+            .flat_map(|[r, g, b]| [r, g, b, 255])
+            .collect();
+        assert_eq!(decoded, expected);
+    }
+}
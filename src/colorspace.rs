@@ -0,0 +1,116 @@
+//! sRGB ↔ linear color conversions (IEC 61966-2-1 piecewise transfer function).
+
+use crate::Colorspace;
+
+/// Converts an 8-bit sRGB-encoded channel value to normalized linear light.
+pub fn srgb_to_linear(s: u8) -> f32 {
+    let s = s as f32 / 255.0;
+    if s <= 0.04045 {
+        s / 12.92
+    } else {
+        ((s + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts a linear light value back to an sRGB-encoded channel value.
+pub fn linear_to_srgb(l: f32) -> u8 {
+    let s = if l <= 0.0031308 {
+        12.92 * l
+    } else {
+        1.055 * l.powf(1.0 / 2.4) - 0.055
+    };
+    (s.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Converts a pixel's color channels to linear light according to `colorspace`.
+/// Alpha is always treated as linear.
+pub fn pixel_to_linear(colorspace: Colorspace, pixel: [u8; 4]) -> [f32; 4] {
+    let [r, g, b, a] = pixel;
+    match colorspace {
+        Colorspace::Srgb => [
+            srgb_to_linear(r),
+            srgb_to_linear(g),
+            srgb_to_linear(b),
+            a as f32 / 255.0,
+        ],
+        Colorspace::Rgb => [
+            r as f32 / 255.0,
+            g as f32 / 255.0,
+            b as f32 / 255.0,
+            a as f32 / 255.0,
+        ],
+    }
+}
+
+/// Converts a linear-light pixel back to `colorspace`. Alpha is always treated as linear.
+pub fn pixel_from_linear(colorspace: Colorspace, pixel: [f32; 4]) -> [u8; 4] {
+    let [r, g, b, a] = pixel;
+    let a_u8 = (a.clamp(0.0, 1.0) * 255.0).round() as u8;
+    match colorspace {
+        Colorspace::Srgb => [
+            linear_to_srgb(r),
+            linear_to_srgb(g),
+            linear_to_srgb(b),
+            a_u8,
+        ],
+        Colorspace::Rgb => [
+            (r.clamp(0.0, 1.0) * 255.0).round() as u8,
+            (g.clamp(0.0, 1.0) * 255.0).round() as u8,
+            (b.clamp(0.0, 1.0) * 255.0).round() as u8,
+            a_u8,
+        ],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srgb_to_linear_extremes() {
+        assert_eq!(srgb_to_linear(0), 0.0);
+        assert!((srgb_to_linear(255) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn srgb_to_linear_threshold() {
+        // Just below the 0.04045 threshold: the linear branch (`s / 12.92`).
+        let below = 10u8; // s = 10/255 = 0.0392... <= 0.04045
+        let s = below as f32 / 255.0;
+        assert!((srgb_to_linear(below) - s / 12.92).abs() < 1e-6);
+
+        // Just above the 0.04045 threshold: the powf branch.
+        let above = 11u8; // s = 11/255 = 0.0431... > 0.04045
+        let s = above as f32 / 255.0;
+        assert!((srgb_to_linear(above) - ((s + 0.055) / 1.055).powf(2.4)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn linear_to_srgb_extremes() {
+        assert_eq!(linear_to_srgb(0.0), 0);
+        assert_eq!(linear_to_srgb(1.0), 255);
+    }
+
+    #[test]
+    fn linear_to_srgb_threshold() {
+        // l == 0.0031308 exactly lands on the linear branch (`12.92 * l`), not the
+        // powf one, per the IEC 61966-2-1 piecewise definition.
+        assert_eq!(linear_to_srgb(0.0031308), (12.92 * 0.0031308 * 255.0).round() as u8);
+    }
+
+    #[test]
+    fn pixel_roundtrip_srgb() {
+        let pixel = [12, 200, 64, 128];
+        let linear = pixel_to_linear(Colorspace::Srgb, pixel);
+        let back = pixel_from_linear(Colorspace::Srgb, linear);
+        assert_eq!(back, pixel);
+    }
+
+    #[test]
+    fn pixel_roundtrip_rgb() {
+        let pixel = [12, 200, 64, 128];
+        let linear = pixel_to_linear(Colorspace::Rgb, pixel);
+        let back = pixel_from_linear(Colorspace::Rgb, linear);
+        assert_eq!(back, pixel);
+    }
+}
@@ -0,0 +1,164 @@
+//! CRC-32 (reflected, polynomial `0xEDB88320`) used by the optional integrity footer.
+
+use std::io::{self, Read, Write};
+
+const fn build_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 == 1 {
+                0xEDB88320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+}
+
+const TABLE: [u32; 256] = build_table();
+
+fn update(crc: u32, byte: u8) -> u32 {
+    TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+}
+
+/// A `Write` adapter that forwards to `inner` while accumulating a running CRC-32
+/// over every byte written.
+pub(crate) struct Crc32Writer<W> {
+    inner: W,
+    crc: u32,
+}
+
+impl<W: Write> Crc32Writer<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        Self {
+            inner,
+            crc: 0xFFFFFFFF,
+        }
+    }
+
+    /// Consumes the adapter, returning the wrapped writer and the finalized CRC-32.
+    pub(crate) fn finish(self) -> (W, u32) {
+        (self.inner, self.crc ^ 0xFFFFFFFF)
+    }
+}
+
+impl<W: Write> Write for Crc32Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        for &byte in &buf[..n] {
+            self.crc = update(self.crc, byte);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A `Read` adapter that forwards to `inner` while accumulating a running CRC-32
+/// over every byte read.
+pub(crate) struct Crc32Reader<R> {
+    inner: R,
+    crc: u32,
+}
+
+impl<R: Read> Crc32Reader<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Self {
+            inner,
+            crc: 0xFFFFFFFF,
+        }
+    }
+
+    /// Consumes the adapter, returning the wrapped reader and the finalized CRC-32.
+    pub(crate) fn finish(self) -> (R, u32) {
+        (self.inner, self.crc ^ 0xFFFFFFFF)
+    }
+}
+
+impl<R: Read> Read for Crc32Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        for &byte in &buf[..n] {
+            self.crc = update(self.crc, byte);
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Channels, Colorspace, Header};
+
+    /// The standard CRC-32/ISO-HDLC check value for the ASCII digits `"123456789"`.
+    #[test]
+    fn known_vector() {
+        let mut writer = Crc32Writer::new(Vec::new());
+        writer.write_all(b"123456789").unwrap();
+        let (_, crc) = writer.finish();
+        assert_eq!(crc, 0xCBF43926);
+    }
+
+    #[test]
+    fn writer_and_reader_agree() {
+        let mut writer = Crc32Writer::new(Vec::new());
+        writer.write_all(b"the quick brown fox").unwrap();
+        let (data, write_crc) = writer.finish();
+
+        let mut reader = Crc32Reader::new(data.as_slice());
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).unwrap();
+        let (_, read_crc) = reader.finish();
+
+        assert_eq!(write_crc, read_crc);
+    }
+
+    #[test]
+    fn crc_roundtrip_ok() {
+        let header = Header {
+            width: 2,
+            height: 1,
+            channels: Channels::Rgba,
+            colorspace: Colorspace::Srgb,
+        };
+        let pixels = [[1, 2, 3, 255], [4, 5, 6, 255]];
+        let mut encoded = Vec::new();
+        crate::encode::encode_checked(header, pixels.into_iter(), &mut encoded).unwrap();
+
+        let mut decoded = Vec::new();
+        let decoded_header = crate::decode::decode_checked(&mut encoded.as_slice(), &mut decoded).unwrap();
+        assert_eq!(decoded_header, header);
+        assert_eq!(decoded, pixels.concat());
+    }
+
+    #[test]
+    fn crc_detects_corruption() {
+        let header = Header {
+            width: 2,
+            height: 1,
+            channels: Channels::Rgba,
+            colorspace: Colorspace::Srgb,
+        };
+        let pixels = [[1, 2, 3, 255], [4, 5, 6, 255]];
+        let mut encoded = Vec::new();
+        crate::encode::encode_checked(header, pixels.into_iter(), &mut encoded).unwrap();
+
+        // Flip a bit in the middle of the encoded pixel data, leaving the header and
+        // trailing CRC footer untouched.
+        let corrupt_index = encoded.len() / 2;
+        encoded[corrupt_index] ^= 0x01;
+
+        let mut decoded = Vec::new();
+        let result = crate::decode::decode_checked(&mut encoded.as_slice(), &mut decoded);
+        assert!(matches!(result, Err(crate::decode::DecodeError::ChecksumMismatch)));
+    }
+}
@@ -5,7 +5,9 @@ use std::{
     path::Path,
 };
 
-use crate::{Channels, Colorspace, Header, qoi_hash};
+use crate::crc32::Crc32Reader;
+use crate::repack::PixelFormat;
+use crate::{Channels, Colorspace, Header, colorspace, qoi_hash, repack};
 
 pub(crate) trait ReadExt: Read {
     fn read_byte(&mut self) -> io::Result<u8> {
@@ -30,6 +32,10 @@ pub enum DecodeError {
     InvalidNumberOfChannels,
     InvalidColorspace,
     InvalidEofSequence,
+    /// The CRC-32 footer read by [`decode_checked`] didn't match the decoded bytes.
+    ChecksumMismatch,
+    /// `width * height` overflows `usize`.
+    InvalidImageSize,
 }
 
 impl Error for DecodeError {
@@ -42,6 +48,15 @@ impl Error for DecodeError {
     }
 }
 
+/// Computes `header.width * header.height`, guarding against the multiplication
+/// overflowing (which would otherwise silently truncate for large dimensions).
+pub(crate) fn n_pixels(header: Header) -> Result<usize, DecodeError> {
+    (header.width as u64)
+        .checked_mul(header.height as u64)
+        .and_then(|n| usize::try_from(n).ok())
+        .ok_or(DecodeError::InvalidImageSize)
+}
+
 pub(crate) fn decode_header(stream: &mut impl Read) -> Result<Header, DecodeError> {
     if stream.read_array::<4>()? != *b"qoif" {
         return Err(DecodeError::InvalidHeader);
@@ -106,6 +121,127 @@ pub(crate) fn read_chunk(bytes: &mut impl Read) -> Result<Chunk, DecodeError> {
     }
 }
 
+/// A single decoded QOI op (OP_RGB, OP_RGBA, OP_INDEX, OP_DIFF, OP_LUMA, or OP_RUN).
+/// Mirrors the crate's internal chunk representation, exposed for tools that want to
+/// inspect the raw op stream rather than just the decoded pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Rgb([u8; 3]),
+    Rgba([u8; 4]),
+    Index(u8),
+    Diff {
+        dr: i8,
+        dg: i8,
+        db: i8,
+    },
+    Luma {
+        dg: i8,
+        /// dr - dg.
+        dr_dg: i8,
+        /// db - dg.
+        db_dg: i8,
+    },
+    Run(u8),
+}
+
+impl From<Chunk> for Op {
+    fn from(chunk: Chunk) -> Self {
+        match chunk {
+            Chunk::Rgb(rgb) => Op::Rgb(rgb),
+            Chunk::Rgba(rgba) => Op::Rgba(rgba),
+            Chunk::Index(index) => Op::Index(index),
+            Chunk::Diff { dr, dg, db } => Op::Diff { dr, dg, db },
+            Chunk::Luma { dg, dr_dg, db_dg } => Op::Luma { dg, dr_dg, db_dg },
+            Chunk::Run(run) => Op::Run(run),
+        }
+    }
+}
+
+/// One [`Op`] yielded by [`ChunkDecoder`], with the byte offset it started at
+/// (relative to the first byte after the header) and the index of the first pixel
+/// it produces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkInfo {
+    pub offset: usize,
+    pub pixel_index: usize,
+    pub op: Op,
+}
+
+struct CountingReader<R> {
+    inner: R,
+    count: usize,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n;
+        Ok(n)
+    }
+}
+
+/// Iterates over the raw QOI op stream (rather than decoded pixels), reporting each
+/// op's byte offset and the pixel index it produces. Useful for validating a stream,
+/// computing statistics (e.g. how much of an image is RUN-compressed), or recovering
+/// whatever ops are readable from a truncated or corrupt file: unlike [`decode`], a
+/// read error on one op doesn't prevent earlier ops already yielded from being used.
+/// [`decode`] and [`PixelDecoder`] can be thought of as layered on top of this op
+/// stream.
+pub struct ChunkDecoder<R: Read> {
+    input: CountingReader<R>,
+    header: Header,
+    n_pixels_total: usize,
+    n_pixels_emitted: usize,
+    finished: bool,
+}
+
+impl<R: Read> ChunkDecoder<R> {
+    /// Reads the `Header` from `input` and prepares to iterate its op stream.
+    pub fn new(mut input: R) -> Result<Self, DecodeError> {
+        let header = decode_header(&mut input)?;
+        let n_pixels_total = n_pixels(header)?;
+        Ok(Self {
+            input: CountingReader { inner: input, count: 0 },
+            header,
+            n_pixels_total,
+            n_pixels_emitted: 0,
+            finished: false,
+        })
+    }
+
+    pub fn header(&self) -> Header {
+        self.header
+    }
+}
+
+impl<R: Read> Iterator for ChunkDecoder<R> {
+    type Item = Result<ChunkInfo, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished || self.n_pixels_emitted >= self.n_pixels_total {
+            return None;
+        }
+        let offset = self.input.count;
+        let chunk = match read_chunk(&mut self.input) {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                self.finished = true;
+                return Some(Err(e));
+            }
+        };
+        let pixel_index = self.n_pixels_emitted;
+        self.n_pixels_emitted += match chunk {
+            Chunk::Run(run) => run as usize,
+            _ => 1,
+        };
+        Some(Ok(ChunkInfo {
+            offset,
+            pixel_index,
+            op: chunk.into(),
+        }))
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub(crate) struct DecoderState {
     pub(crate) header: Header,
@@ -126,14 +262,15 @@ impl DecoderState {
     }
 }
 
-/// Returns `true` if is end of byte stream.
-pub(crate) fn decode_chunk(
-    state: &mut DecoderState,
-    stream: &mut impl Read,
-    output: &mut impl Write,
-) -> Result<(), DecodeError> {
-    let chunk = read_chunk(stream)?;
-    let current_pixel: [u8; 4] = match chunk {
+/// Reconstructs the pixel produced by a non-`Run` `chunk` against `state`, updating
+/// `state.index_array`/`state.previous_pixel` to match. `Chunk::Run` isn't a single
+/// pixel (it's `run` repeats of `state.previous_pixel`, and per the QOI spec doesn't
+/// touch the index array or previous-pixel), so callers handle it themselves and this
+/// just returns `state.previous_pixel` unchanged for it. Shared by [`decode_chunk`]
+/// and [`PixelDecoder::next_pixel`] so the OP_RGB/RGBA/INDEX/DIFF/LUMA arithmetic only
+/// lives in one place.
+fn apply_chunk(state: &mut DecoderState, chunk: Chunk) -> [u8; 4] {
+    let pixel = match chunk {
         Chunk::Rgb(rgb) => [rgb[0], rgb[1], rgb[2], state.previous_pixel[3]],
         Chunk::Rgba(rgba) => rgba,
         Chunk::Index(index) => state.index_array[index as usize],
@@ -149,24 +286,37 @@ pub(crate) fn decode_chunk(
             state.previous_pixel[2].wrapping_add_signed(db_dg + dg),
             state.previous_pixel[3],
         ],
-        Chunk::Run(run) => {
-            for _ in 0..run {
-                match state.header.channels {
-                    Channels::Rgb => output.write_all(&state.previous_pixel[0..=2])?,
-                    Channels::Rgba => output.write_all(&state.previous_pixel)?,
-                }
+        Chunk::Run(_) => return state.previous_pixel,
+    };
+    let index = qoi_hash(pixel);
+    state.index_array[index] = pixel;
+    state.previous_pixel = pixel;
+    pixel
+}
+
+/// Reads one chunk from `stream`, writes the pixel(s) it produces to `output`, and
+/// advances `state.n_pixels` by that many.
+pub(crate) fn decode_chunk(
+    state: &mut DecoderState,
+    stream: &mut impl Read,
+    output: &mut impl Write,
+) -> Result<(), DecodeError> {
+    let chunk = read_chunk(stream)?;
+    if let Chunk::Run(run) = chunk {
+        for _ in 0..run {
+            match state.header.channels {
+                Channels::Rgb => output.write_all(&state.previous_pixel[0..=2])?,
+                Channels::Rgba => output.write_all(&state.previous_pixel)?,
             }
-            state.n_pixels += run as usize;
-            return Ok(());
         }
-    };
-    let index = qoi_hash(current_pixel);
-    state.index_array[index] = current_pixel;
-    state.previous_pixel = current_pixel;
+        state.n_pixels += run as usize;
+        return Ok(());
+    }
+    let pixel = apply_chunk(state, chunk);
     state.n_pixels += 1;
     match state.header.channels {
-        Channels::Rgb => output.write_all(&state.previous_pixel[0..=2])?,
-        Channels::Rgba => output.write_all(&state.previous_pixel)?,
+        Channels::Rgb => output.write_all(&pixel[0..=2])?,
+        Channels::Rgba => output.write_all(&pixel)?,
     }
     Ok(())
 }
@@ -181,27 +331,133 @@ pub(crate) fn verify_eof_sequence(bytes: &mut impl Read) -> Result<(), DecodeErr
     Ok(())
 }
 
+/// A pull-based, incremental decoder that yields pixels one at a time without
+/// buffering the whole image, for consuming QOI data from a socket or huge file
+/// with bounded memory.
+pub struct PixelDecoder<R: Read> {
+    input: R,
+    state: DecoderState,
+    n_pixels_total: usize,
+    /// Remaining pixels of an in-progress `Chunk::Run` not yet yielded.
+    run_remaining: u8,
+}
+
+impl<R: Read> PixelDecoder<R> {
+    /// Reads the `Header` from `input` and prepares to decode its pixels.
+    pub fn new(mut input: R) -> Result<Self, DecodeError> {
+        let header = decode_header(&mut input)?;
+        let n_pixels_total = n_pixels(header)?;
+        Ok(Self {
+            input,
+            state: DecoderState::new(header),
+            n_pixels_total,
+            run_remaining: 0,
+        })
+    }
+
+    pub fn header(&self) -> Header {
+        self.state.header
+    }
+
+    fn next_pixel(&mut self) -> Result<[u8; 4], DecodeError> {
+        if self.run_remaining > 0 {
+            self.run_remaining -= 1;
+            return Ok(self.state.previous_pixel);
+        }
+        let chunk = read_chunk(&mut self.input)?;
+        if let Chunk::Run(run) = chunk {
+            self.run_remaining = run - 1;
+            return Ok(self.state.previous_pixel);
+        }
+        Ok(apply_chunk(&mut self.state, chunk))
+    }
+}
+
+/// Alias for [`PixelDecoder`], the streaming decoder counterpart to
+/// [`crate::QoiEncoder`].
+pub type QoiDecoder<R> = PixelDecoder<R>;
+
+impl<R: Read> Iterator for PixelDecoder<R> {
+    type Item = Result<[u8; 4], DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.state.n_pixels >= self.n_pixels_total {
+            return None;
+        }
+        let pixel = match self.next_pixel() {
+            Ok(pixel) => pixel,
+            Err(e) => return Some(Err(e)),
+        };
+        self.state.n_pixels += 1;
+        if self.state.n_pixels == self.n_pixels_total {
+            if let Err(e) = verify_eof_sequence(&mut self.input) {
+                return Some(Err(e));
+            }
+        }
+        Some(Ok(pixel))
+    }
+}
+
 pub fn decode(input: &mut impl Read, output: &mut impl Write) -> Result<Header, DecodeError> {
     let header = decode_header(input)?;
-    let n_pixels = (header.width * header.height) as usize;
+    let total_pixels = n_pixels(header)?;
     let mut decoder_state = DecoderState::new(header);
-    while decoder_state.n_pixels < n_pixels {
+    while decoder_state.n_pixels < total_pixels {
         decode_chunk(&mut decoder_state, input, output)?;
     }
     verify_eof_sequence(input)?;
     Ok(header)
 }
 
+/// Like [`decode`], but additionally verifies the trailing CRC-32 footer appended by
+/// [`crate::encode_checked`] and returns [`DecodeError::ChecksumMismatch`] if it
+/// doesn't match. Only use this with streams known to have been encoded with a CRC
+/// footer; plain `.qoi` streams will fail to read the extra 4 bytes.
+pub fn decode_checked(input: &mut impl Read, output: &mut impl Write) -> Result<Header, DecodeError> {
+    let mut crc_reader = Crc32Reader::new(input);
+    let header = decode(&mut crc_reader, output)?;
+    let (input, crc) = crc_reader.finish();
+    let expected = u32::from_be_bytes(input.read_array::<4>()?);
+    if crc != expected {
+        return Err(DecodeError::ChecksumMismatch);
+    }
+    Ok(header)
+}
+
+/// Like [`decode_to_vec`], but packs pixels into `target_format` (e.g. force RGBA
+/// even for RGB inputs, drop alpha, swap to BGRA byte order, or convert sRGB/linear)
+/// as they come off the decode loop, instead of decoding into the canonical layout
+/// and repacking it as a second pass.
+pub fn decode_to_vec_as(
+    input: &mut impl Read,
+    target_format: PixelFormat,
+) -> Result<(Vec<u8>, Header), DecodeError> {
+    let decoder = PixelDecoder::new(input)?;
+    let header = decoder.header();
+    let src_colorspace = header.colorspace;
+    let dst_colorspace = repack::colorspace_of(target_format);
+    let mut data = Vec::with_capacity(n_pixels(header)? * repack::channel_count(target_format));
+    for pixel in decoder {
+        let mut pixel = pixel?;
+        if src_colorspace != dst_colorspace {
+            let linear = colorspace::pixel_to_linear(src_colorspace, pixel);
+            pixel = colorspace::pixel_from_linear(dst_colorspace, linear);
+        }
+        repack::pack_into(target_format, pixel, &mut data);
+    }
+    Ok((data, header))
+}
+
 pub fn decode_to_vec(input: &mut impl Read) -> Result<(Vec<u8>, Header), DecodeError> {
     let mut data = Vec::new();
     let mut cursor = Cursor::new(&mut data);
-    let header = decode(input, &mut cursor).unwrap();
+    let header = decode(input, &mut cursor)?;
     Ok((data, header))
 }
 
 pub fn decode_from_data(data: &[u8]) -> Result<(Vec<u8>, Header), DecodeError> {
     let mut output = Vec::new();
-    let header = decode(&mut Cursor::new(data), &mut Cursor::new(&mut output)).unwrap();
+    let header = decode(&mut Cursor::new(data), &mut Cursor::new(&mut output))?;
     Ok((output, header))
 }
 
@@ -213,10 +469,9 @@ pub fn decode_from_file(
         .read(true)
         .write(false)
         .create(false)
-        .open(&path)
-        .unwrap();
+        .open(&path)?;
     let mut reader = BufReader::new(file);
-    let header = decode(&mut reader, output).unwrap();
+    let header = decode(&mut reader, output)?;
     Ok(header)
 }
 
@@ -225,11 +480,74 @@ pub fn decode_from_file_to_vec(path: impl AsRef<Path>) -> Result<(Vec<u8>, Heade
         .read(true)
         .write(false)
         .create(false)
-        .open(&path)
-        .unwrap();
+        .open(&path)?;
     let mut reader = BufReader::new(file);
     let mut data = Vec::new();
     let mut cursor = Cursor::new(&mut data);
-    let header = decode(&mut reader, &mut cursor).unwrap();
+    let header = decode(&mut reader, &mut cursor)?;
     Ok((data, header))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_header() -> Header {
+        Header {
+            width: 4,
+            height: 1,
+            channels: Channels::Rgba,
+            colorspace: Colorspace::Srgb,
+        }
+    }
+
+    /// A run long enough to span several `Iterator::next()` calls, so
+    /// `PixelDecoder::run_remaining` has to survive being read piecemeal rather than
+    /// all at once.
+    fn sample_pixels() -> Vec<[u8; 4]> {
+        vec![[1, 2, 3, 255], [1, 2, 3, 255], [1, 2, 3, 255], [9, 8, 7, 255]]
+    }
+
+    #[test]
+    fn pixel_decoder_carries_run_across_next_calls() {
+        let header = sample_header();
+        let pixels = sample_pixels();
+        let mut encoded = Vec::new();
+        crate::encode::encode(header, pixels.iter().copied(), &mut encoded).unwrap();
+
+        let decoder = PixelDecoder::new(encoded.as_slice()).unwrap();
+        assert_eq!(decoder.header(), header);
+        let decoded: Result<Vec<[u8; 4]>, DecodeError> = decoder.collect();
+        assert_eq!(decoded.unwrap(), pixels);
+    }
+
+    /// On a truncated stream, `ChunkDecoder` must still yield every op that was fully
+    /// readable before the cut, then surface the read error for the one that wasn't,
+    /// rather than losing the earlier ops like a one-shot `decode()` would.
+    #[test]
+    fn chunk_decoder_yields_partial_ops_on_truncation() {
+        let header = sample_header();
+        // Each pixel's alpha differs from the previous one, which forces every chunk
+        // to be a 5-byte `Chunk::Rgba` (the only op that can change alpha), so cutting
+        // mid-chunk below lands inside one deterministically.
+        let pixels = vec![[1u8, 2, 3, 10], [4, 5, 6, 20], [7, 8, 9, 30], [10, 11, 12, 40]];
+        let mut encoded = Vec::new();
+        crate::encode::encode(header, pixels.iter().copied(), &mut encoded).unwrap();
+
+        // Drop the 8-byte end-of-stream marker entirely, plus 3 more bytes eating into
+        // the final 5-byte `Chunk::Rgba`, so only its opcode and first channel byte
+        // remain readable.
+        let truncated = &encoded[..encoded.len() - 11];
+
+        let decoder = ChunkDecoder::new(truncated).unwrap();
+        assert_eq!(decoder.header(), header);
+        let results: Vec<_> = decoder.collect();
+
+        let (oks, errs): (Vec<_>, Vec<_>) = results.into_iter().partition(Result::is_ok);
+        assert!(!oks.is_empty(), "earlier, fully-readable ops should still be yielded");
+        assert_eq!(errs.len(), 1);
+
+        let pixel_indices: Vec<usize> = oks.into_iter().map(|info| info.unwrap().pixel_index).collect();
+        assert!(pixel_indices.windows(2).all(|w| w[0] < w[1]));
+    }
+}
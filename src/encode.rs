@@ -6,6 +6,7 @@ use std::{
     path::Path,
 };
 
+use crate::crc32::Crc32Writer;
 use crate::{Channels, Header, qoi_hash};
 
 pub fn encode(
@@ -23,6 +24,22 @@ pub fn encode(
     Ok(())
 }
 
+/// Like [`encode`], but appends a trailing CRC-32 (reflected, polynomial
+/// `0xEDB88320`) over all encoded bytes (including the end marker) after the stream.
+/// Plain QOI readers ignore trailing bytes, so this stays backward compatible;
+/// pair with [`crate::decode_checked`] to verify it.
+pub fn encode_checked(
+    header: Header,
+    pixels: impl Iterator<Item = [u8; 4]>,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let mut crc_writer = Crc32Writer::new(output);
+    encode(header, pixels, &mut crc_writer)?;
+    let (output, crc) = crc_writer.finish();
+    output.write_all(&crc.to_be_bytes())?;
+    Ok(())
+}
+
 pub fn encode_from_slice(header: Header, slice: &[u8], output: &mut impl Write) -> io::Result<()> {
     match header.channels {
         Channels::Rgb => {
@@ -117,7 +134,18 @@ impl<W: Write> Encoder<W> {
         let pixel = pixels.next().unwrap();
         if let Some(byte) = self.try_run(pixel, pixels) {
             self.output.write_all(&[byte])?;
-        } else if pixel[3] != self.previous_pixel[3] {
+        } else {
+            self.encode_pixel_chunk(pixel)?;
+        }
+        self.previous_pixel = pixel;
+        self.index_array[qoi_hash(pixel)] = pixel;
+        Ok(())
+    }
+
+    /// Encodes `pixel` using OP_RGBA/INDEX/DIFF/LUMA/RGB (never OP_RUN) and writes
+    /// the resulting bytes to `self.output`.
+    pub(crate) fn encode_pixel_chunk(&mut self, pixel: [u8; 4]) -> io::Result<()> {
+        if pixel[3] != self.previous_pixel[3] {
             // All other methods require currnet alpha = previous alpha.
             let bytes = self.encode_with_op_rgba(pixel);
             self.output.write_all(&bytes)?;
@@ -133,8 +161,6 @@ impl<W: Write> Encoder<W> {
             let bytes = self.encode_with_op_rgba(pixel);
             self.output.write_all(&bytes)?;
         }
-        self.previous_pixel = pixel;
-        self.index_array[qoi_hash(pixel)] = pixel;
         Ok(())
     }
 
@@ -221,3 +247,91 @@ impl<W: Write> Encoder<W> {
         Ok(())
     }
 }
+
+/// An incremental, push-based encoder: pixels are pushed in one at a time and QOI
+/// chunks are written out as soon as they're determined, so the whole image never
+/// needs to be collected into an iterator up front. This lets callers transcode
+/// very large images or pipe through sockets with bounded memory. The streaming
+/// decoder counterpart is [`crate::QoiDecoder`].
+pub struct QoiEncoder<W: Write> {
+    inner: Encoder<W>,
+    /// Pixels identical to `inner.previous_pixel` pushed so far but not yet
+    /// flushed as a `Chunk::Run`.
+    pending_run: u8,
+}
+
+impl<W: Write> QoiEncoder<W> {
+    /// Writes the `Header` and prepares to accept pushed pixels.
+    pub fn new(header: Header, output: W) -> io::Result<Self> {
+        let mut inner = Encoder::new(header, output);
+        inner.encode_header()?;
+        Ok(Self {
+            inner,
+            pending_run: 0,
+        })
+    }
+
+    fn flush_pending_run(&mut self) -> io::Result<()> {
+        if self.pending_run > 0 {
+            let byte = (0b11 << 6) | (self.pending_run - 1);
+            self.inner.output.write_all(&[byte])?;
+            self.pending_run = 0;
+        }
+        Ok(())
+    }
+
+    /// Pushes one pixel into the stream.
+    pub fn push_pixel(&mut self, pixel: [u8; 4]) -> io::Result<()> {
+        if pixel == self.inner.previous_pixel {
+            self.pending_run += 1;
+            if self.pending_run == 62 {
+                self.flush_pending_run()?;
+            }
+            return Ok(());
+        }
+        self.flush_pending_run()?;
+        self.inner.encode_pixel_chunk(pixel)?;
+        self.inner.previous_pixel = pixel;
+        self.inner.index_array[qoi_hash(pixel)] = pixel;
+        Ok(())
+    }
+
+    /// Flushes any pending run and writes the end-of-stream marker.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.flush_pending_run()?;
+        self.inner.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decode::decode_from_data;
+
+    /// Pushes more than 62 pixels of a repeated run (past the single-byte `Chunk::Run`
+    /// limit) plus a trailing non-matching pixel, so `pending_run` has to flush both
+    /// mid-stream (at the 62 cap) and on a pixel mismatch, and the decoded result must
+    /// still match what was pushed.
+    #[test]
+    fn qoi_encoder_accumulates_pending_run() {
+        let header = Header {
+            width: 65,
+            height: 1,
+            channels: Channels::Rgba,
+            colorspace: crate::Colorspace::Srgb,
+        };
+        let mut pixels = vec![[1u8, 2, 3, 255]; 64];
+        pixels.push([9, 8, 7, 255]);
+
+        let mut encoded = Vec::new();
+        let mut encoder = QoiEncoder::new(header, &mut encoded).unwrap();
+        for &pixel in &pixels {
+            encoder.push_pixel(pixel).unwrap();
+        }
+        encoder.finish().unwrap();
+
+        let (decoded, decoded_header) = decode_from_data(&encoded).unwrap();
+        assert_eq!(decoded_header, header);
+        assert_eq!(decoded, pixels.concat());
+    }
+}
@@ -1,7 +1,11 @@
 #![feature(array_chunks)]
 
+pub mod bmp;
+pub mod colorspace;
+mod crc32;
 mod decode;
 mod encode;
+pub mod repack;
 
 pub use decode::*;
 pub use encode::*;
@@ -6,8 +6,12 @@ use std::{
     path::Path,
 };
 
+pub mod bmp;
+pub mod colorspace;
+pub mod crc32;
 pub mod decode;
 pub mod encode;
+pub mod repack;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct Header {
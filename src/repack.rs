@@ -0,0 +1,116 @@
+//! Pixel repacking: channel permutation, alpha insertion/removal, and sRGB/linear
+//! conversion between [`PixelFormat`]s, decoupled from any particular image writer.
+
+use crate::Colorspace;
+use crate::colorspace::{pixel_from_linear, pixel_to_linear};
+
+/// A pixel's channel layout and colorspace, independent of any particular image
+/// format's encoding. Used to describe both BMP's native layouts and arbitrary
+/// caller-requested output layouts (e.g. [`crate::decode_to_vec_as`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    Rgb8,
+    Rgb8Srgb,
+    Rgba8,
+    Rgba8Srgb,
+    Bgra8,
+    Bgra8Srgb,
+}
+
+/// Number of bytes stored per pixel for `format`.
+pub(crate) fn channel_count(format: PixelFormat) -> usize {
+    match format {
+        PixelFormat::Rgb8 | PixelFormat::Rgb8Srgb => 3,
+        PixelFormat::Rgba8 | PixelFormat::Rgba8Srgb | PixelFormat::Bgra8 | PixelFormat::Bgra8Srgb => 4,
+    }
+}
+
+pub(crate) fn colorspace_of(format: PixelFormat) -> Colorspace {
+    match format {
+        PixelFormat::Rgb8Srgb | PixelFormat::Rgba8Srgb | PixelFormat::Bgra8Srgb => Colorspace::Srgb,
+        PixelFormat::Rgb8 | PixelFormat::Rgba8 | PixelFormat::Bgra8 => Colorspace::Rgb,
+    }
+}
+
+/// Unpacks one pixel of `format` from `src` into canonical RGBA order, filling a
+/// missing alpha channel with `alpha_fill`.
+pub(crate) fn unpack(format: PixelFormat, src: &[u8], alpha_fill: u8) -> [u8; 4] {
+    match format {
+        PixelFormat::Rgb8 | PixelFormat::Rgb8Srgb => [src[0], src[1], src[2], alpha_fill],
+        PixelFormat::Rgba8 | PixelFormat::Rgba8Srgb => [src[0], src[1], src[2], src[3]],
+        PixelFormat::Bgra8 | PixelFormat::Bgra8Srgb => [src[2], src[1], src[0], src[3]],
+    }
+}
+
+/// Packs one canonical RGBA pixel into `format`'s native channel layout, appending it
+/// to `dst` in place so callers packing many pixels (e.g. a whole decoded image) don't
+/// pay for one small heap allocation per pixel.
+pub(crate) fn pack_into(format: PixelFormat, rgba: [u8; 4], dst: &mut Vec<u8>) {
+    match format {
+        PixelFormat::Rgb8 | PixelFormat::Rgb8Srgb => dst.extend_from_slice(&[rgba[0], rgba[1], rgba[2]]),
+        PixelFormat::Rgba8 | PixelFormat::Rgba8Srgb => dst.extend_from_slice(&rgba),
+        PixelFormat::Bgra8 | PixelFormat::Bgra8Srgb => {
+            dst.extend_from_slice(&[rgba[2], rgba[1], rgba[0], rgba[3]])
+        }
+    }
+}
+
+/// Converts a buffer of pixels from `src_format` to `dst_format`: permutes channels
+/// (RGB/RGBA/BGRA), inserts or drops the alpha channel (filling with `alpha_fill` when
+/// `dst_format` has alpha but `src_format` doesn't), and converts between sRGB and
+/// linear when the two formats declare different colorspaces.
+pub fn convert_with_alpha_fill(
+    src_format: PixelFormat,
+    dst_format: PixelFormat,
+    src: &[u8],
+    alpha_fill: u8,
+) -> Vec<u8> {
+    let src_channels = channel_count(src_format);
+    let src_colorspace = colorspace_of(src_format);
+    let dst_colorspace = colorspace_of(dst_format);
+    let mut dst = Vec::with_capacity((src.len() / src_channels) * channel_count(dst_format));
+    for chunk in src.chunks_exact(src_channels) {
+        let mut rgba = unpack(src_format, chunk, alpha_fill);
+        if src_colorspace != dst_colorspace {
+            let linear = pixel_to_linear(src_colorspace, rgba);
+            rgba = pixel_from_linear(dst_colorspace, linear);
+        }
+        pack_into(dst_format, rgba, &mut dst);
+    }
+    dst
+}
+
+/// Converts a buffer of pixels from `src_format` to `dst_format`, filling any newly
+/// inserted alpha channel as fully opaque (`255`). See [`convert_with_alpha_fill`] to
+/// control the fill value.
+pub fn convert(src_format: PixelFormat, dst_format: PixelFormat, src: &[u8]) -> Vec<u8> {
+    convert_with_alpha_fill(src_format, dst_format, src, 255)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rgb_to_rgba_fills_custom_alpha() {
+        let rgb = [10u8, 20, 30, 40, 50, 60];
+        let rgba = convert_with_alpha_fill(PixelFormat::Rgb8, PixelFormat::Rgba8, &rgb, 128);
+        assert_eq!(rgba, vec![10, 20, 30, 128, 40, 50, 60, 128]);
+    }
+
+    #[test]
+    fn rgba_to_rgb_drops_alpha() {
+        let rgba = [10u8, 20, 30, 255, 40, 50, 60, 0];
+        let rgb = convert(PixelFormat::Rgba8, PixelFormat::Rgb8, &rgba);
+        assert_eq!(rgb, vec![10, 20, 30, 40, 50, 60]);
+    }
+
+    #[test]
+    fn rgba_bgra_roundtrip() {
+        let rgba = [10u8, 20, 30, 40, 50, 60, 70, 80];
+        let bgra = convert(PixelFormat::Rgba8, PixelFormat::Bgra8, &rgba);
+        assert_eq!(bgra, vec![30, 20, 10, 40, 70, 60, 50, 80]);
+        let back = convert(PixelFormat::Bgra8, PixelFormat::Rgba8, &bgra);
+        assert_eq!(back, rgba);
+    }
+}